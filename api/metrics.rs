@@ -0,0 +1,24 @@
+#[path = "../src/shared.rs"]
+mod shared;
+
+use hyper::{Response, StatusCode};
+use vercel_runtime::{Error, Request, ResponseBody};
+
+use shared::metrics_handle;
+
+/// Expose the Prometheus registry in the text exposition format so operators
+/// can see scrape counts, per-site latency, run duration, and in-flight
+/// permits without grepping logs.
+async fn handler(_request: Request) -> Result<Response<ResponseBody>, Error> {
+    let body = metrics_handle().render();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; version=0.0.4; charset=utf-8")
+        .body(ResponseBody::from(body))
+        .map_err(Into::into)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    vercel_runtime::run(vercel_runtime::service_fn::<_, (Request,)>(handler)).await
+}
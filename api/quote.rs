@@ -9,7 +9,7 @@ use serde_json::json;
 use uuid::Uuid;
 use vercel_runtime::{Error, Request, ResponseBody};
 
-use shared::{QuoteRequest, QuoteResponse, run_quote_collect};
+use shared::{QuoteRequest, QuoteResponse, callback_url, deliver_webhook, run_quote_collect};
 
 fn json_response(status: StatusCode, body: serde_json::Value) -> Result<Response<ResponseBody>, Error> {
     Response::builder()
@@ -52,14 +52,27 @@ async fn handler(request: Request) -> Result<Response<ResponseBody>, Error> {
         }
     };
 
+    let duration_ms = started.elapsed().as_millis();
+    shared::record_run(duration_ms);
+
     let response = QuoteResponse {
-        run_id,
+        run_id: run_id.clone(),
         started_at,
-        duration_ms: started.elapsed().as_millis(),
+        duration_ms,
         items,
     };
 
-    json_response(StatusCode::OK, serde_json::to_value(response)?)
+    let value = serde_json::to_value(&response)?;
+
+    // Push the finished quote to the caller's webhook, if any, without
+    // blocking the HTTP response.
+    if let Some(url) = callback_url(&payload) {
+        if let Ok(body) = serde_json::to_vec(&response) {
+            tokio::spawn(deliver_webhook(url, run_id, body));
+        }
+    }
+
+    json_response(StatusCode::OK, value)
 }
 
 #[tokio::main]
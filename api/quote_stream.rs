@@ -16,12 +16,22 @@ use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 use vercel_runtime::{Error, Request, ResponseBody};
 
-use shared::{QuoteRequest, best_from_matches, scrape_site};
+use shared::{JobStore, QuoteRequest, best_from_matches, heartbeat_secs, job_store, scrape_site};
 
 fn sse_line(payload: serde_json::Value) -> String {
     format!("data: {}\n\n", payload)
 }
 
+/// An SSE frame carrying its sequence `id:` so browsers auto-resume with
+/// `Last-Event-ID` after a dropped connection.
+fn sse_event(seq: u64, payload: &serde_json::Value) -> String {
+    format!("id: {}\ndata: {}\n\n", seq, payload)
+}
+
+fn sse_comment(text: &str) -> String {
+    format!(": {}\n\n", text)
+}
+
 fn response_sse(status: StatusCode, body: String) -> Result<Response<ResponseBody>, Error> {
     Response::builder()
         .status(status)
@@ -31,30 +41,32 @@ fn response_sse(status: StatusCode, body: String) -> Result<Response<ResponseBod
         .map_err(Into::into)
 }
 
-async fn handler(request: Request) -> Result<Response<ResponseBody>, Error> {
-    if request.method() != "POST" {
-        return response_sse(
-            StatusCode::METHOD_NOT_ALLOWED,
-            sse_line(json!({"type":"error","message":"method_not_allowed"})),
-        );
+/// Resolve the `after` sequence the client wants to resume from, preferring the
+/// standard `Last-Event-ID` header and falling back to an `?after=` query param.
+fn resume_after(request: &Request) -> Option<u64> {
+    if let Some(value) = request.headers().get("last-event-id") {
+        if let Some(seq) = value.to_str().ok().and_then(|v| v.trim().parse::<u64>().ok()) {
+            return Some(seq);
+        }
     }
+    query_param(request, "after").and_then(|v| v.parse::<u64>().ok())
+}
 
-    let body = request.into_body().collect().await?.to_bytes();
-    let payload: QuoteRequest = match serde_json::from_slice(&body) {
-        Ok(data) => data,
-        Err(error) => {
-            return response_sse(
-                StatusCode::BAD_REQUEST,
-                sse_line(json!({"type":"error","message":format!("invalid_json: {error}")})),
-            );
+fn query_param(request: &Request, key: &str) -> Option<String> {
+    let query = request.uri().query()?;
+    for pair in query.split('&') {
+        let mut parts = pair.splitn(2, '=');
+        if parts.next() == Some(key) {
+            let raw = parts.next().unwrap_or("");
+            return Some(urlencoding::decode(raw).map(|c| c.into_owned()).unwrap_or_else(|_| raw.to_string()));
         }
-    };
+    }
+    None
+}
 
-    let run_id = payload
-        .run_id
-        .clone()
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
-    let started_at = Utc::now().to_rfc3339();
+/// Drain a `QuoteRequest` into the store, appending one event per SSE frame.
+/// Runs detached from any connection so a dropped client never loses progress.
+async fn run_worker(store: Arc<dyn JobStore>, run_id: String, started_at: String, payload: QuoteRequest) {
     let started = Instant::now();
     let ttl = payload
         .options
@@ -69,74 +81,204 @@ async fn handler(request: Request) -> Result<Response<ResponseBody>, Error> {
 
     let semaphore = Arc::new(Semaphore::new(20));
     let overrides = payload.site_overrides.clone();
-    let (tx, rx) = mpsc::channel::<Result<Frame<Bytes>, Error>>(64);
+    let http = shared::http_config(&payload);
 
-    tokio::spawn(async move {
-        let _ = tx
-            .send(Ok(Frame::data(Bytes::from(sse_line(json!({
-                "type": "started",
-                "run_id": run_id,
-                "started_at": started_at
-            }))))))
-            .await;
-
-        for (item_index, item) in payload.items.iter().enumerate() {
-            let query = item.query.clone();
-            let tasks = payload.site_plan.iter().cloned().map(|site| {
-                let sem = semaphore.clone();
-                let query_clone = query.clone();
-                let overrides = overrides.clone();
-                let site_clone = site.clone();
-                async move {
-                    let permit = sem.acquire_owned().await;
-                    let Ok(_permit) = permit else {
-                        return shared::SiteMatch {
-                            site: site_clone.clone(),
-                            title: None,
-                            price: None,
-                            currency: Some("USD".to_string()),
-                            url: None,
-                            status: "error".to_string(),
-                            message: Some("semaphore_closed".to_string()),
-                            latency_ms: Some(0)
-                        };
+    store
+        .append(
+            &run_id,
+            json!({ "type": "started", "run_id": run_id, "started_at": started_at }),
+        )
+        .await;
+
+    for (item_index, item) in payload.items.iter().enumerate() {
+        let query = item.query.clone();
+        let tasks = payload.site_plan.iter().cloned().map(|site| {
+            let sem = semaphore.clone();
+            let query_clone = query.clone();
+            let overrides = overrides.clone();
+            let site_clone = site.clone();
+            let http = http;
+            async move {
+                let permit = sem.acquire_owned().await;
+                let Ok(_permit) = permit else {
+                    return shared::SiteMatch {
+                        site: site_clone.clone(),
+                        title: None,
+                        price: None,
+                        currency: Some("USD".to_string()),
+                        url: None,
+                        status: "error".to_string(),
+                        message: Some("semaphore_closed".to_string()),
+                        latency_ms: Some(0),
+                        product_id: None,
                     };
-                    scrape_site(&site, &query_clone, ttl, overrides.as_ref()).await
-                }
-            });
-
-            let mut matches = Vec::new();
-            let mut scrape_stream = stream::iter(tasks).buffer_unordered(20);
-
-            while let Some(result) = scrape_stream.next().await {
-                let _ = tx
-                    .send(Ok(Frame::data(Bytes::from(sse_line(json!({
-                        "type": "match",
-                        "item_index": item_index,
-                        "query": query,
-                        "match": result
-                    }))))))
-                    .await;
-                matches.push(result);
+                };
+                scrape_site(&site, &query_clone, ttl, overrides.as_ref(), &http).await
             }
+        });
+
+        let mut matches = Vec::new();
+        let mut scrape_stream = stream::iter(tasks).buffer_unordered(20);
 
-            let best = best_from_matches(&matches);
-            let _ = tx
-                .send(Ok(Frame::data(Bytes::from(sse_line(json!({
-                    "type": "item_done",
-                    "item_index": item_index,
-                    "query": query,
-                    "best": best
-                }))))))
+        while let Some(result) = scrape_stream.next().await {
+            shared::record_scrape(&result.site, &result.status, result.latency_ms);
+            shared::record_inflight(20 - semaphore.available_permits());
+            store
+                .append(
+                    &run_id,
+                    json!({ "type": "match", "item_index": item_index, "query": query, "match": result }),
+                )
                 .await;
+            matches.push(result);
         }
 
-        let _ = tx
-            .send(Ok(Frame::data(Bytes::from(sse_line(json!({
-                "type": "done",
-                "duration_ms": started.elapsed().as_millis()
-            }))))))
-            .await;
+        let best = best_from_matches(&matches);
+        let item_done = json!({ "type": "item_done", "item_index": item_index, "query": query, "best": best });
+        store.append(&run_id, item_done.clone()).await;
+
+        // For clients that can't hold the SSE connection open, push each
+        // best result to the configured webhook as it lands.
+        if let Some(url) = shared::callback_url(&payload) {
+            if let Ok(body) = serde_json::to_vec(&item_done) {
+                tokio::spawn(shared::deliver_webhook(url, run_id.clone(), body));
+            }
+        }
+    }
+
+    let duration_ms = started.elapsed().as_millis();
+    shared::record_run(duration_ms);
+    store
+        .append(&run_id, json!({ "type": "done", "duration_ms": duration_ms }))
+        .await;
+    store.finish(&run_id).await;
+}
+
+/// Tail a run from the store into the SSE channel: replay everything past
+/// `after`, then follow live appends until the run finishes. A heartbeat keeps
+/// proxies from dropping an idle connection.
+async fn tail_run(
+    store: Arc<dyn JobStore>,
+    run_id: String,
+    after: u64,
+    heartbeat: u64,
+    tx: mpsc::Sender<Result<Frame<Bytes>, Error>>,
+) {
+    let started = Instant::now();
+    let heartbeat_handle = if heartbeat > 0 {
+        let hb_tx = tx.clone();
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(tokio::time::Duration::from_secs(heartbeat));
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let frame = sse_comment("ping")
+                    + &sse_line(json!({ "type": "heartbeat", "elapsed_ms": started.elapsed().as_millis() }));
+                if hb_tx.send(Ok(Frame::data(Bytes::from(frame)))).await.is_err() {
+                    break;
+                }
+            }
+        }))
+    } else {
+        None
+    };
+
+    let mut cursor = after;
+    loop {
+        for (seq, event) in store.events_after(&run_id, cursor).await {
+            if tx
+                .send(Ok(Frame::data(Bytes::from(sse_event(seq, &event)))))
+                .await
+                .is_err()
+            {
+                // Client went away again; the worker keeps filling the store.
+                if let Some(handle) = heartbeat_handle {
+                    handle.abort();
+                }
+                return;
+            }
+            cursor = seq;
+        }
+
+        if store.is_finished(&run_id).await {
+            // Flush anything appended between the last drain and the finish flag.
+            for (seq, event) in store.events_after(&run_id, cursor).await {
+                let _ = tx.send(Ok(Frame::data(Bytes::from(sse_event(seq, &event))))).await;
+                cursor = seq;
+            }
+            break;
+        }
+
+        // Advisory wakeup; cap the wait so a lost notify can't stall the tail.
+        let _ = tokio::time::timeout(
+            tokio::time::Duration::from_secs(1),
+            store.notified(&run_id),
+        )
+        .await;
+    }
+
+    if let Some(handle) = heartbeat_handle {
+        handle.abort();
+    }
+}
+
+async fn handler(request: Request) -> Result<Response<ResponseBody>, Error> {
+    if request.method() != "POST" {
+        return response_sse(
+            StatusCode::METHOD_NOT_ALLOWED,
+            sse_line(json!({"type":"error","message":"method_not_allowed"})),
+        );
+    }
+
+    let after = resume_after(&request);
+    let reconnect = after.is_some();
+    let query_run_id = query_param(&request, "run_id");
+
+    let body = request.into_body().collect().await?.to_bytes();
+    let payload: QuoteRequest = match serde_json::from_slice(&body) {
+        Ok(data) => data,
+        Err(error) => {
+            // A bare reconnect (Last-Event-ID, empty body) carries no payload.
+            if reconnect {
+                QuoteRequest {
+                    run_id: query_run_id.clone(),
+                    items: Vec::new(),
+                    category: String::new(),
+                    site_plan: Vec::new(),
+                    site_overrides: None,
+                    options: None,
+                }
+            } else {
+                return response_sse(
+                    StatusCode::BAD_REQUEST,
+                    sse_line(json!({"type":"error","message":format!("invalid_json: {error}")})),
+                );
+            }
+        }
+    };
+
+    let run_id = query_run_id
+        .or_else(|| payload.run_id.clone())
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let started_at = Utc::now().to_rfc3339();
+    let heartbeat = heartbeat_secs(&payload);
+
+    let store = job_store();
+    if !reconnect {
+        // New run: enqueue the scrape; it drains into the store independently
+        // of whether this connection survives.
+        let worker_store = store.clone();
+        let worker_run_id = run_id.clone();
+        let worker_started_at = started_at.clone();
+        tokio::spawn(async move {
+            run_worker(worker_store, worker_run_id, worker_started_at, payload).await;
+        });
+    }
+
+    let (tx, rx) = mpsc::channel::<Result<Frame<Bytes>, Error>>(64);
+    let tail_store = store.clone();
+    let tail_run_id = run_id.clone();
+    tokio::spawn(async move {
+        tail_run(tail_store, tail_run_id, after.unwrap_or(0), heartbeat, tx).await;
     });
 
     let stream_body = StreamBody::new(ReceiverStream::new(rx));
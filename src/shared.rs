@@ -22,6 +22,11 @@ pub struct QuoteItem {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuoteOptions {
     pub cache_ttl: Option<u64>,
+    pub heartbeat_secs: Option<u64>,
+    pub max_retries: Option<usize>,
+    pub timeout_ms: Option<u64>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub callback_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +49,8 @@ pub struct SiteMatch {
     pub status: String,
     pub message: Option<String>,
     pub latency_ms: Option<u128>,
+    /// Stable product identifier (EAN/UPC/GTIN/SKU/MPN/ASIN), if extracted.
+    pub product_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -131,21 +138,28 @@ fn first_capture(body: &str, pattern: &str) -> Option<String> {
 }
 
 fn extract_result_url(site: &str, body: &str) -> Option<String> {
-    match site {
-        "amazon" | "amazon_business" => {
-            let path = first_capture(body, r#"href=\"(/(?:gp|dp|[^"]*?/dp/)[^"]+)\""#)?;
-            Some(format!("https://www.amazon.com{}", path.replace("\\u0026", "&")))
+    adapter_for(site).extract_product_url(body)
+}
+
+/// A stable product identifier from JSON-LD, falling back to Amazon's `data-asin`.
+fn extract_product_id(site: &str, body: &str) -> Option<String> {
+    for field in ["gtin13", "gtin14", "gtin12", "gtin", "mpn", "sku"] {
+        if let Some(value) = first_capture(
+            body,
+            &format!(r#""{field}"\s*:\s*"?([A-Za-z0-9][A-Za-z0-9._-]{{3,}})"?"#),
+        ) {
+            return Some(format!("{field}:{value}"));
         }
-        "newegg" => first_capture(body, r#"href=\"(https://www\.newegg\.com/p/[^\"]+)\""#),
-        "bestbuy" => {
-            let path = first_capture(body, r#"href=\"(/site/[^"]+\.p\?[^"]*)\""#)
-                .or_else(|| first_capture(body, r#"href=\"(/site/[^"]+\.p)\""#))?;
-            Some(format!("https://www.bestbuy.com{}", path.replace("\\u0026", "&")))
+    }
+
+    if matches!(site, "amazon" | "amazon_business") {
+        if let Some(asin) = first_capture(body, r#"data-asin=\"([A-Z0-9]{10})\""#) {
+            return Some(format!("asin:{asin}"));
         }
-        "ebay" => first_capture(body, r#"href=\"(https://www\.ebay\.com/itm/[^\"]+)\""#),
-        "target" => first_capture(body, r#"href=\"(https://www\.target\.com/p/[^\"]+)\""#),
-        _ => None
     }
+
+    first_capture(body, r#"data-model=\"([A-Za-z0-9][A-Za-z0-9._/-]{3,})\""#)
+        .map(|model| format!("model:{model}"))
 }
 
 fn extract_price_from_json_ld(body: &str) -> Option<f64> {
@@ -213,16 +227,18 @@ fn extract_amazon_price(body: &str) -> Option<f64> {
 }
 
 fn extract_price_from_body(site: &str, body: &str) -> Option<f64> {
+    adapter_for(site).extract_price(body)
+}
+
+/// JSON-LD price, falling back to the generic `$`-regex heuristic.
+fn extract_price_generic(body: &str) -> Option<f64> {
     if let Some(json_ld_price) = extract_price_from_json_ld(body) {
         return Some(json_ld_price);
     }
+    extract_price_regex(body)
+}
 
-    if matches!(site, "amazon" | "amazon_business") {
-        if let Some(price) = extract_amazon_price(body) {
-            return Some(price);
-        }
-    }
-
+fn extract_price_regex(body: &str) -> Option<f64> {
     let price_regex = Regex::new(r"\$\s?([0-9]{1,3}(?:,[0-9]{3})*(?:\.[0-9]{2})?)").ok()?;
     let lower = body.to_lowercase();
     let mut candidates: Vec<f64> = Vec::new();
@@ -274,6 +290,335 @@ fn extract_price_from_body(site: &str, body: &str) -> Option<f64> {
     candidates.into_iter().find(|price| *price >= median * 0.35).or(Some(median))
 }
 
+/// VTEX `commertialOffer` block carrying the seller's price and availability.
+#[derive(Debug, Deserialize)]
+struct VtexOffer {
+    #[serde(rename = "Price")]
+    price: Option<f64>,
+    #[serde(rename = "IsAvailable")]
+    is_available: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VtexSeller {
+    #[serde(rename = "commertialOffer")]
+    offer: Option<VtexOffer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VtexItem {
+    sellers: Option<Vec<VtexSeller>>,
+}
+
+/// A single product from VTEX's `catalog_system` search response.
+#[derive(Debug, Deserialize)]
+struct VtexProduct {
+    #[serde(rename = "productName")]
+    product_name: Option<String>,
+    link: Option<String>,
+    items: Option<Vec<VtexItem>>,
+}
+
+/// VTEX catalog_system search endpoint, opt-in via a `"<site>.api"` override.
+fn structured_api_url(site: &str, query: &str, overrides: Option<&HashMap<String, String>>) -> Option<String> {
+    let q = encode(query);
+    let map = overrides?;
+    let template = map.get(&format!("{site}.api"))?;
+    Some(template.replace("{q}", &q))
+}
+
+/// Fetch and parse a site's JSON product API, falling back to HTML on any failure.
+async fn scrape_structured(
+    site: &str,
+    query: &str,
+    overrides: Option<&HashMap<String, String>>,
+    http: &HttpConfig,
+    start: Instant,
+) -> Option<SiteMatch> {
+    let api_url = structured_api_url(site, query, overrides)?;
+    let client = http_client(http.pool_max_idle_per_host);
+    let ua = pick_user_agent(site, query, 0);
+    let request = client.get(&api_url).headers(build_headers_with_ua(ua));
+    let response = timeout(Duration::from_millis(http.timeout_ms), request.send())
+        .await
+        .ok()?
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+
+    let products: Vec<VtexProduct> = response.json().await.ok()?;
+    let product = products.into_iter().next()?;
+    let price = product
+        .items
+        .as_ref()
+        .and_then(|items| items.first())
+        .and_then(|item| item.sellers.as_ref())
+        .and_then(|sellers| sellers.first())
+        .and_then(|seller| seller.offer.as_ref())
+        .filter(|offer| offer.is_available != Some(false))
+        .and_then(|offer| offer.price)?;
+
+    // `product.link` is VTEX's absolute canonical page URL; when it's absent,
+    // fall back to the search URL rather than a relative slug guess or the raw
+    // API endpoint so `SiteMatch.url` stays an absolute, clickable link like
+    // every other site's.
+    let url = product.link.unwrap_or_else(|| site_url(site, query, overrides));
+
+    Some(SiteMatch {
+        site: site.to_string(),
+        title: product.product_name,
+        price: Some(price),
+        currency: Some("USD".to_string()),
+        url: Some(url),
+        status: "ok".to_string(),
+        message: Some("structured_api".to_string()),
+        latency_ms: Some(start.elapsed().as_millis()),
+        product_id: None,
+    })
+}
+
+/// Whether the headless-browser fallback is enabled, via `HEADLESS_FALLBACK`.
+fn headless_enabled() -> bool {
+    matches!(
+        std::env::var("HEADLESS_FALLBACK").ok().as_deref(),
+        Some("1") | Some("true")
+    )
+}
+
+/// Bound on concurrent browser contexts, shared across all scrapes.
+fn headless_semaphore() -> Arc<Semaphore> {
+    static SEM: std::sync::OnceLock<Arc<Semaphore>> = std::sync::OnceLock::new();
+    SEM.get_or_init(|| {
+        let permits = std::env::var("HEADLESS_MAX_CONTEXTS")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or(2);
+        Arc::new(Semaphore::new(permits.max(1)))
+    })
+    .clone()
+}
+
+/// Render `url` through a headless browser and return the rendered HTML.
+#[cfg(feature = "headless")]
+async fn headless_fetch(url: &str) -> Option<String> {
+    use chromiumoxide::browser::{Browser, BrowserConfig};
+    use futures::StreamExt;
+
+    if !headless_enabled() {
+        return None;
+    }
+    let _permit = headless_semaphore().acquire_owned().await.ok()?;
+
+    let (mut browser, mut handler) = Browser::launch(BrowserConfig::builder().build().ok()?).await.ok()?;
+    let driver = tokio::spawn(async move { while handler.next().await.is_some() {} });
+
+    let rendered = async {
+        let page = browser.new_page(url).await.ok()?;
+        page.wait_for_navigation().await.ok()?;
+        page.content().await.ok()
+    }
+    .await;
+
+    let _ = browser.close().await;
+    driver.abort();
+    rendered
+}
+
+#[cfg(not(feature = "headless"))]
+async fn headless_fetch(_url: &str) -> Option<String> {
+    None
+}
+
+/// Tunables for the shared scrape HTTP client and its retry policy.
+#[derive(Debug, Clone, Copy)]
+pub struct HttpConfig {
+    pub max_retries: usize,
+    pub timeout_ms: u64,
+    pub pool_max_idle_per_host: usize,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            max_retries: 2,
+            timeout_ms: 5000,
+            pool_max_idle_per_host: 8,
+        }
+    }
+}
+
+/// Upper bounds on caller-supplied `QuoteOptions` HTTP knobs.
+const MAX_RETRIES_CEILING: usize = 5;
+const TIMEOUT_MS_CEILING: u64 = 30_000;
+const POOL_MAX_IDLE_PER_HOST_CEILING: usize = 32;
+
+pub fn http_config(req: &QuoteRequest) -> HttpConfig {
+    let mut config = HttpConfig::default();
+    if let Some(options) = &req.options {
+        if let Some(max_retries) = options.max_retries {
+            config.max_retries = max_retries.min(MAX_RETRIES_CEILING);
+        }
+        if let Some(timeout_ms) = options.timeout_ms {
+            config.timeout_ms = timeout_ms.clamp(1, TIMEOUT_MS_CEILING);
+        }
+        if let Some(pool) = options.pool_max_idle_per_host {
+            config.pool_max_idle_per_host = pool.clamp(1, POOL_MAX_IDLE_PER_HOST_CEILING);
+        }
+    }
+    if let Ok(value) = std::env::var("SCRAPE_MAX_RETRIES") {
+        if let Ok(parsed) = value.parse::<usize>() {
+            config.max_retries = parsed.min(MAX_RETRIES_CEILING);
+        }
+    }
+    config
+}
+
+/// Shared, connection-pooling HTTP client keyed by pool size.
+fn http_client(pool_max_idle_per_host: usize) -> reqwest::Client {
+    http_client_for(pool_max_idle_per_host, None)
+}
+
+/// Like [`http_client`] but optionally routed through `proxy`.
+fn http_client_for(pool_max_idle_per_host: usize, proxy: Option<&str>) -> reqwest::Client {
+    static CLIENTS: std::sync::OnceLock<std::sync::Mutex<HashMap<(usize, String), reqwest::Client>>> =
+        std::sync::OnceLock::new();
+    let clients = CLIENTS.get_or_init(|| std::sync::Mutex::new(HashMap::new()));
+    let key = (pool_max_idle_per_host, proxy.unwrap_or("").to_string());
+    let mut guard = clients.lock().unwrap();
+    guard
+        .entry(key)
+        .or_insert_with(|| {
+            let mut builder = reqwest::Client::builder()
+                .use_rustls_tls()
+                .pool_max_idle_per_host(pool_max_idle_per_host);
+            if let Some(proxy) = proxy {
+                if let Ok(proxy) = reqwest::Proxy::all(proxy) {
+                    builder = builder.proxy(proxy);
+                }
+            }
+            builder.build().unwrap_or_else(|_| reqwest::Client::new())
+        })
+        .clone()
+}
+
+struct ProxyState {
+    consecutive_failures: usize,
+    skip_until: Option<Instant>,
+}
+
+/// Pool of egress proxies read from `PROXIES`, with cooldown for failing ones.
+struct ProxyPool {
+    proxies: Vec<String>,
+    state: std::sync::Mutex<HashMap<String, ProxyState>>,
+}
+
+impl ProxyPool {
+    fn from_env() -> Self {
+        let proxies = std::env::var("PROXIES")
+            .unwrap_or_default()
+            .split([',', '\n'])
+            .map(|proxy| proxy.trim().to_string())
+            .filter(|proxy| !proxy.is_empty())
+            .collect();
+        ProxyPool {
+            proxies,
+            state: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pick a proxy for this attempt, skipping any currently in cooldown.
+    fn pick(&self, site: &str, query: &str, attempt: usize) -> Option<String> {
+        if self.proxies.is_empty() {
+            return None;
+        }
+        let state = self.state.lock().unwrap();
+        let available: Vec<&String> = self
+            .proxies
+            .iter()
+            .filter(|proxy| {
+                state
+                    .get(*proxy)
+                    .and_then(|entry| entry.skip_until)
+                    .map(|until| until <= Instant::now())
+                    .unwrap_or(true)
+            })
+            .collect();
+        if available.is_empty() {
+            return None;
+        }
+        let mut seed: usize = attempt;
+        for byte in site.bytes().chain(query.bytes()) {
+            seed = seed.wrapping_mul(131).wrapping_add(byte as usize);
+        }
+        Some(available[seed % available.len()].clone())
+    }
+
+    fn record_failure(&self, proxy: &str) {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entry(proxy.to_string()).or_insert(ProxyState {
+            consecutive_failures: 0,
+            skip_until: None,
+        });
+        entry.consecutive_failures += 1;
+        if entry.consecutive_failures >= 3 {
+            entry.consecutive_failures = 0;
+            entry.skip_until = Some(Instant::now() + Duration::from_secs(60));
+        }
+    }
+
+    fn record_success(&self, proxy: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(entry) = state.get_mut(proxy) {
+            entry.consecutive_failures = 0;
+            entry.skip_until = None;
+        }
+    }
+}
+
+fn proxy_pool() -> &'static ProxyPool {
+    static POOL: std::sync::OnceLock<ProxyPool> = std::sync::OnceLock::new();
+    POOL.get_or_init(ProxyPool::from_env)
+}
+
+/// Strip embedded basic-auth credentials from a proxy URL.
+fn redact_proxy(proxy: &str) -> String {
+    match reqwest::Url::parse(proxy) {
+        Ok(mut url) if !url.username().is_empty() || url.password().is_some() => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        Ok(_) => proxy.to_string(),
+        Err(_) => proxy.to_string(),
+    }
+}
+
+/// Render the chosen proxy for a failure message, empty when none was used.
+fn proxy_suffix(proxy: &Option<String>) -> String {
+    match proxy {
+        Some(proxy) => format!(" via proxy {}", redact_proxy(proxy)),
+        None => String::new(),
+    }
+}
+
+/// Exponential backoff (base 200ms, capped at 2s) with deterministic jitter.
+fn backoff_delay(attempt: usize, site: &str, query: &str) -> Duration {
+    let base = 200u64.saturating_mul(1 << attempt.min(4));
+    let capped = base.min(2000);
+    let mut seed: u64 = attempt as u64;
+    for byte in site.bytes().chain(query.bytes()) {
+        seed = seed.wrapping_mul(131).wrapping_add(byte as u64);
+    }
+    Duration::from_millis(capped + seed % 100)
+}
+
+/// Parse a `Retry-After` delay (seconds form), capped to a few seconds.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let raw = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    raw.trim().parse::<u64>().ok().map(|secs| Duration::from_secs(secs.min(5)))
+}
+
 fn build_headers_with_ua(user_agent: &str) -> HeaderMap {
     let mut headers = HeaderMap::new();
     if let Ok(value) = HeaderValue::from_str(user_agent) {
@@ -290,41 +635,158 @@ fn build_headers_with_ua(user_agent: &str) -> HeaderMap {
 }
 
 fn site_url(site: &str, query: &str, overrides: Option<&HashMap<String, String>>) -> String {
-    let q = encode(query);
+    // A `site_overrides` entry overrides the adapter's URL template directly.
     if let Some(map) = overrides {
         if let Some(template) = map.get(site) {
-            return template.replace("{q}", &q);
-        }
-    }
-    match site {
-        "amazon" | "amazon_business" => format!("https://www.amazon.com/s?k={q}"),
-        "bestbuy" => format!("https://www.bestbuy.com/site/searchpage.jsp?st={q}"),
-        "newegg" => format!("https://www.newegg.com/p/pl?d={q}"),
-        "bhphotovideo" => format!("https://www.bhphotovideo.com/c/search?q={q}"),
-        "walmart" | "walmart_business" => format!("https://www.walmart.com/search?q={q}"),
-        "staples" => format!("https://www.staples.com/{q}/directory_{q}"),
-        "officedepot" => format!("https://www.officedepot.com/a/search/?q={q}"),
-        "quill" => format!("https://www.quill.com/search?keywords={q}"),
-        "uline" => format!("https://www.uline.com/BL_35/Search?keywords={q}"),
-        "target" => format!("https://www.target.com/s?searchTerm={q}"),
-        "webstaurantstore" => format!("https://www.webstaurantstore.com/search/{q}.html"),
-        "katom" => format!("https://www.katom.com/search.html?query={q}"),
-        "centralrestaurant" => format!("https://www.centralrestaurant.com/search/{q}"),
-        "therestaurantstore" => format!("https://www.therestaurantstore.com/search/{q}"),
-        "restaurantdepot" => format!("https://www.restaurantdepot.com/catalogsearch/result/?q={q}"),
-        "ace_mart" => format!("https://www.acemart.com/search?q={q}"),
-        "grainger" => format!("https://www.grainger.com/search?searchQuery={q}"),
-        "zoro" => format!("https://www.zoro.com/search?q={q}"),
-        "homedepot" => format!("https://www.homedepot.com/s/{q}"),
-        "platt" => format!("https://www.platt.com/search.aspx?q={q}"),
-        "cityelectricsupply" => format!("https://www.cityelectricsupply.com/search?text={q}"),
-        "lowes" => format!("https://www.lowes.com/search?searchTerm={q}"),
-        "mcmaster" => format!("https://www.mcmaster.com/products/{q}/"),
-        "adorama" => format!("https://www.adorama.com/l/?searchinfo={q}"),
-        "microcenter" => format!("https://www.microcenter.com/search/search_results.aspx?Ntt={q}"),
-        "ebay" => format!("https://www.ebay.com/sch/i.html?_nkw={q}"),
-        _ => format!("https://www.google.com/search?q={q}+buy")
+            return template.replace("{q}", &encode(query));
+        }
     }
+    adapter_for(site).search_url(query)
+}
+
+/// A retailer's scrape behavior, registered via [`build_registry`].
+pub trait SiteAdapter: Send + Sync {
+    fn search_url(&self, query: &str) -> String;
+
+    fn extract_price(&self, body: &str) -> Option<f64> {
+        extract_price_generic(body)
+    }
+
+    fn extract_product_url(&self, _body: &str) -> Option<String> {
+        None
+    }
+
+    fn requires_js(&self) -> bool {
+        false
+    }
+}
+
+/// Generic adapter: a URL template plus an optional product-URL extractor.
+struct GenericAdapter {
+    template: &'static str,
+    product_url: fn(&str) -> Option<String>,
+}
+
+impl SiteAdapter for GenericAdapter {
+    fn search_url(&self, query: &str) -> String {
+        self.template.replace("{q}", &encode(query))
+    }
+
+    fn extract_product_url(&self, body: &str) -> Option<String> {
+        (self.product_url)(body)
+    }
+}
+
+/// Amazon storefronts: a-price markup plus `/dp/` product links.
+struct AmazonAdapter;
+
+impl SiteAdapter for AmazonAdapter {
+    fn search_url(&self, query: &str) -> String {
+        format!("https://www.amazon.com/s?k={}", encode(query))
+    }
+
+    fn extract_price(&self, body: &str) -> Option<f64> {
+        extract_price_from_json_ld(body)
+            .or_else(|| extract_amazon_price(body))
+            .or_else(|| extract_price_regex(body))
+    }
+
+    fn extract_product_url(&self, body: &str) -> Option<String> {
+        amazon_product_url(body)
+    }
+}
+
+/// Fallback adapter: a Google product search.
+struct GoogleAdapter;
+
+impl SiteAdapter for GoogleAdapter {
+    fn search_url(&self, query: &str) -> String {
+        format!("https://www.google.com/search?q={}+buy", encode(query))
+    }
+}
+
+fn no_product_url(_body: &str) -> Option<String> {
+    None
+}
+
+fn amazon_product_url(body: &str) -> Option<String> {
+    let path = first_capture(body, r#"href=\"(/(?:gp|dp|[^"]*?/dp/)[^"]+)\""#)?;
+    Some(format!("https://www.amazon.com{}", path.replace("\\u0026", "&")))
+}
+
+fn newegg_product_url(body: &str) -> Option<String> {
+    first_capture(body, r#"href=\"(https://www\.newegg\.com/p/[^\"]+)\""#)
+}
+
+fn bestbuy_product_url(body: &str) -> Option<String> {
+    let path = first_capture(body, r#"href=\"(/site/[^"]+\.p\?[^"]*)\""#)
+        .or_else(|| first_capture(body, r#"href=\"(/site/[^"]+\.p)\""#))?;
+    Some(format!("https://www.bestbuy.com{}", path.replace("\\u0026", "&")))
+}
+
+fn ebay_product_url(body: &str) -> Option<String> {
+    first_capture(body, r#"href=\"(https://www\.ebay\.com/itm/[^\"]+)\""#)
+}
+
+fn target_product_url(body: &str) -> Option<String> {
+    first_capture(body, r#"href=\"(https://www\.target\.com/p/[^\"]+)\""#)
+}
+
+/// Build the site-adapter registry.
+fn build_registry() -> HashMap<&'static str, Box<dyn SiteAdapter>> {
+    let mut registry: HashMap<&'static str, Box<dyn SiteAdapter>> = HashMap::new();
+    registry.insert("amazon", Box::new(AmazonAdapter));
+    registry.insert("amazon_business", Box::new(AmazonAdapter));
+
+    let generic: &[(&'static str, &'static str, fn(&str) -> Option<String>)] = &[
+        ("bestbuy", "https://www.bestbuy.com/site/searchpage.jsp?st={q}", bestbuy_product_url),
+        ("newegg", "https://www.newegg.com/p/pl?d={q}", newegg_product_url),
+        ("ebay", "https://www.ebay.com/sch/i.html?_nkw={q}", ebay_product_url),
+        ("target", "https://www.target.com/s?searchTerm={q}", target_product_url),
+        ("bhphotovideo", "https://www.bhphotovideo.com/c/search?q={q}", no_product_url),
+        ("walmart", "https://www.walmart.com/search?q={q}", no_product_url),
+        ("walmart_business", "https://www.walmart.com/search?q={q}", no_product_url),
+        ("staples", "https://www.staples.com/{q}/directory_{q}", no_product_url),
+        ("officedepot", "https://www.officedepot.com/a/search/?q={q}", no_product_url),
+        ("quill", "https://www.quill.com/search?keywords={q}", no_product_url),
+        ("uline", "https://www.uline.com/BL_35/Search?keywords={q}", no_product_url),
+        ("webstaurantstore", "https://www.webstaurantstore.com/search/{q}.html", no_product_url),
+        ("katom", "https://www.katom.com/search.html?query={q}", no_product_url),
+        ("centralrestaurant", "https://www.centralrestaurant.com/search/{q}", no_product_url),
+        ("therestaurantstore", "https://www.therestaurantstore.com/search/{q}", no_product_url),
+        ("restaurantdepot", "https://www.restaurantdepot.com/catalogsearch/result/?q={q}", no_product_url),
+        ("ace_mart", "https://www.acemart.com/search?q={q}", no_product_url),
+        ("grainger", "https://www.grainger.com/search?searchQuery={q}", no_product_url),
+        ("zoro", "https://www.zoro.com/search?q={q}", no_product_url),
+        ("homedepot", "https://www.homedepot.com/s/{q}", no_product_url),
+        ("platt", "https://www.platt.com/search.aspx?q={q}", no_product_url),
+        ("cityelectricsupply", "https://www.cityelectricsupply.com/search?text={q}", no_product_url),
+        ("lowes", "https://www.lowes.com/search?searchTerm={q}", no_product_url),
+        ("mcmaster", "https://www.mcmaster.com/products/{q}/", no_product_url),
+        ("adorama", "https://www.adorama.com/l/?searchinfo={q}", no_product_url),
+        ("microcenter", "https://www.microcenter.com/search/search_results.aspx?Ntt={q}", no_product_url),
+    ];
+    for (key, template, product_url) in generic {
+        registry.insert(key, Box::new(GenericAdapter { template, product_url: *product_url }));
+    }
+    registry
+}
+
+fn registry() -> &'static HashMap<&'static str, Box<dyn SiteAdapter>> {
+    static REGISTRY: std::sync::OnceLock<HashMap<&'static str, Box<dyn SiteAdapter>>> =
+        std::sync::OnceLock::new();
+    REGISTRY.get_or_init(build_registry)
+}
+
+/// The adapter for `site`, falling back to the Google adapter for unknown keys.
+fn adapter_for(site: &str) -> &'static dyn SiteAdapter {
+    static GOOGLE: GoogleAdapter = GoogleAdapter;
+    registry().get(site).map(|adapter| adapter.as_ref()).unwrap_or(&GOOGLE)
+}
+
+/// Whether the registered adapter for `site` wants the headless fallback.
+fn site_requires_js(site: &str) -> bool {
+    adapter_for(site).requires_js()
 }
 
 fn query_hash(query: &str) -> String {
@@ -345,6 +807,19 @@ fn ttl_seconds(req: &QuoteRequest) -> u64 {
         .unwrap_or(0)
 }
 
+/// Seconds between SSE keep-alive frames; `0` disables the heartbeat.
+pub fn heartbeat_secs(req: &QuoteRequest) -> u64 {
+    if let Some(options) = &req.options {
+        if let Some(secs) = options.heartbeat_secs {
+            return secs;
+        }
+    }
+    std::env::var("SSE_HEARTBEAT_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(15)
+}
+
 pub async fn maybe_get_cache(site: &str, query: &str, ttl: u64) -> Option<SiteMatch> {
     if ttl == 0 {
         return None;
@@ -424,10 +899,438 @@ pub async fn upsert_cache(site: &str, query: &str, payload: &SiteMatch) {
         .await;
 }
 
-pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&HashMap<String, String>>) -> SiteMatch {
+/// Install the process-wide Prometheus recorder once, falling back to an inert
+/// handle if installation fails rather than panicking.
+pub fn metrics_handle() -> metrics_exporter_prometheus::PrometheusHandle {
+    static HANDLE: std::sync::OnceLock<metrics_exporter_prometheus::PrometheusHandle> =
+        std::sync::OnceLock::new();
+    HANDLE
+        .get_or_init(|| {
+            metrics_exporter_prometheus::PrometheusBuilder::new()
+                .install_recorder()
+                .unwrap_or_else(|_| metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder().handle())
+        })
+        .clone()
+}
+
+/// Record one completed scrape's status and latency.
+pub fn record_scrape(site: &str, status: &str, latency_ms: Option<u128>) {
+    metrics_handle();
+    metrics::counter!("scrapes_total", "site" => site.to_string(), "status" => status.to_string())
+        .increment(1);
+    if let Some(latency) = latency_ms {
+        metrics::histogram!("scrape_latency_ms", "site" => site.to_string()).record(latency as f64);
+    }
+}
+
+/// Record the wall-clock duration of a finished run at the `done` frame.
+pub fn record_run(duration_ms: u128) {
+    metrics_handle();
+    metrics::histogram!("run_duration_ms").record(duration_ms as f64);
+}
+
+/// Track how many scrape permits are currently in use.
+pub fn record_inflight(in_use: usize) {
+    metrics_handle();
+    metrics::gauge!("scrape_inflight_permits").set(in_use as f64);
+}
+
+/// Lower-case a query with collapsed whitespace.
+fn normalize_query(query: &str) -> String {
+    query.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Normalized cache key for a scrape: `(site, normalized_query)`.
+pub fn cache_key(site: &str, query: &str) -> String {
+    format!("{site}:{}", normalize_query(query))
+}
+
+/// Durable, cross-invocation cache for a single site's `SiteMatch`.
+#[async_trait::async_trait]
+pub trait QuoteCache: Send + Sync {
+    async fn get(&self, key: &str) -> Option<SiteMatch>;
+    async fn put(&self, key: &str, value: SiteMatch, ttl: u64);
+}
+
+/// Process-local cache with per-entry expiry.
+#[derive(Default)]
+pub struct MemoryQuoteCache {
+    entries: std::sync::Mutex<HashMap<String, (SiteMatch, Instant, u64)>>,
+}
+
+#[async_trait::async_trait]
+impl QuoteCache for MemoryQuoteCache {
+    async fn get(&self, key: &str) -> Option<SiteMatch> {
+        let entries = self.entries.lock().unwrap();
+        let (value, stored_at, ttl) = entries.get(key)?;
+        if stored_at.elapsed().as_secs() > *ttl {
+            return None;
+        }
+        Some(value.clone())
+    }
+
+    async fn put(&self, key: &str, value: SiteMatch, ttl: u64) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (value, Instant::now(), ttl));
+    }
+}
+
+/// Redis-backed `QuoteCache`, selected by `CACHE_BACKEND=redis`.
+#[cfg(feature = "redis")]
+pub struct RedisQuoteCache {
+    client: redis::Client,
+}
+
+#[cfg(feature = "redis")]
+impl RedisQuoteCache {
+    pub fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait::async_trait]
+impl QuoteCache for RedisQuoteCache {
+    async fn get(&self, key: &str) -> Option<SiteMatch> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.ok()?;
+        let raw: Option<String> = redis::cmd("GET")
+            .arg(format!("quote:cache:{key}"))
+            .query_async(&mut conn)
+            .await
+            .ok()?;
+        serde_json::from_str(&raw?).ok()
+    }
+
+    async fn put(&self, key: &str, value: SiteMatch, ttl: u64) {
+        let Ok(mut conn) = self.client.get_multiplexed_async_connection().await else {
+            return;
+        };
+        let Ok(payload) = serde_json::to_string(&value) else {
+            return;
+        };
+        let _: Result<(), _> = redis::cmd("SET")
+            .arg(format!("quote:cache:{key}"))
+            .arg(payload)
+            .arg("EX")
+            .arg(ttl.max(1))
+            .query_async(&mut conn)
+            .await;
+    }
+}
+
+/// S3-compatible `QuoteCache`, selected by `CACHE_BACKEND=s3`.
+#[cfg(feature = "s3")]
+pub struct S3QuoteCache {
+    bucket: rusty_s3::Bucket,
+    credentials: rusty_s3::Credentials,
+}
+
+#[cfg(feature = "s3")]
+impl S3QuoteCache {
+    pub fn from_env() -> Option<Self> {
+        let endpoint = std::env::var("CACHE_S3_ENDPOINT").ok()?;
+        let region = std::env::var("CACHE_S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+        let name = std::env::var("CACHE_S3_BUCKET").ok()?;
+        let access = std::env::var("CACHE_S3_ACCESS_KEY").ok()?;
+        let secret = std::env::var("CACHE_S3_SECRET_KEY").ok()?;
+        let bucket = rusty_s3::Bucket::new(
+            endpoint.parse().ok()?,
+            rusty_s3::UrlStyle::Path,
+            name,
+            region,
+        )
+        .ok()?;
+        Some(Self {
+            bucket,
+            credentials: rusty_s3::Credentials::new(access, secret),
+        })
+    }
+
+    fn object(key: &str) -> String {
+        format!("cache/{}.json", query_hash(key))
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait::async_trait]
+impl QuoteCache for S3QuoteCache {
+    async fn get(&self, key: &str) -> Option<SiteMatch> {
+        use rusty_s3::S3Action;
+        let action = self.bucket.get_object(Some(&self.credentials), &Self::object(key));
+        let url = action.sign(Duration::from_secs(60));
+        let response = reqwest::Client::new().get(url).send().await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let envelope: serde_json::Value = response.json().await.ok()?;
+        let stored_at = envelope.get("stored_at")?.as_i64()?;
+        let ttl = envelope.get("ttl")?.as_u64()?;
+        let now = chrono::Utc::now().timestamp();
+        if now - stored_at > ttl as i64 {
+            return None;
+        }
+        serde_json::from_value(envelope.get("value")?.clone()).ok()
+    }
+
+    async fn put(&self, key: &str, value: SiteMatch, ttl: u64) {
+        use rusty_s3::S3Action;
+        let envelope = json!({
+            "value": value,
+            "ttl": ttl,
+            "stored_at": chrono::Utc::now().timestamp()
+        });
+        let Ok(body) = serde_json::to_vec(&envelope) else {
+            return;
+        };
+        let action = self.bucket.put_object(Some(&self.credentials), &Self::object(key));
+        let url = action.sign(Duration::from_secs(60));
+        let _ = reqwest::Client::new().put(url).body(body).send().await;
+    }
+}
+
+/// A single observed price for a product at a point in time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub fetched_at: i64,
+    pub price: f64,
+}
+
+/// Observed prices for a `(site, query)` over a window, plus the minimum seen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceHistory {
+    pub observations: Vec<PricePoint>,
+    pub min: Option<f64>,
+}
+
+/// Local SQLite `QuoteCache`, selected by `CACHE_BACKEND=sqlite`. Also appends
+/// every successful scrape to `price_history`.
+#[cfg(feature = "sqlite")]
+pub struct SqliteQuoteCache {
+    pool: sqlx::SqlitePool,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteQuoteCache {
+    pub async fn connect(path: &str) -> Result<Self> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect(&format!("sqlite://{path}?mode=rwc"))
+            .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS price_cache (
+                key TEXT PRIMARY KEY,
+                payload TEXT NOT NULL,
+                stored_at INTEGER NOT NULL,
+                ttl INTEGER NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS price_history (
+                site TEXT NOT NULL,
+                query_hash TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL,
+                price REAL NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        Ok(Self { pool })
+    }
+
+    /// Build the pool lazily (no `await`) so it can be constructed from the
+    /// synchronous [`quote_cache`] selector, creating the schema in a spawned
+    /// task on first use.
+    pub fn connect_lazy(path: &str) -> Result<Self> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect_lazy(&format!("sqlite://{path}?mode=rwc"))?;
+        let schema_pool = pool.clone();
+        tokio::spawn(async move {
+            for ddl in [
+                "CREATE TABLE IF NOT EXISTS price_cache (
+                    key TEXT PRIMARY KEY,
+                    payload TEXT NOT NULL,
+                    stored_at INTEGER NOT NULL,
+                    ttl INTEGER NOT NULL
+                )",
+                "CREATE TABLE IF NOT EXISTS price_history (
+                    site TEXT NOT NULL,
+                    query_hash TEXT NOT NULL,
+                    fetched_at INTEGER NOT NULL,
+                    price REAL NOT NULL
+                )",
+            ] {
+                let _ = sqlx::query(ddl).execute(&schema_pool).await;
+            }
+        });
+        Ok(Self { pool })
+    }
+
+    async fn price_history(&self, site: &str, query: &str, since: i64) -> PriceHistory {
+        let hash = query_hash(&normalize_query(query));
+        let rows: Vec<(i64, f64)> = sqlx::query_as(
+            "SELECT fetched_at, price FROM price_history
+             WHERE site = ?1 AND query_hash = ?2 AND fetched_at >= ?3
+             ORDER BY fetched_at ASC",
+        )
+        .bind(site)
+        .bind(&hash)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .unwrap_or_default();
+
+        let observations: Vec<PricePoint> = rows
+            .into_iter()
+            .map(|(fetched_at, price)| PricePoint { fetched_at, price })
+            .collect();
+        let min = observations
+            .iter()
+            .map(|point| point.price)
+            .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        PriceHistory { observations, min }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[async_trait::async_trait]
+impl QuoteCache for SqliteQuoteCache {
+    async fn get(&self, key: &str) -> Option<SiteMatch> {
+        let row: Option<(String, i64, i64)> =
+            sqlx::query_as("SELECT payload, stored_at, ttl FROM price_cache WHERE key = ?1")
+                .bind(key)
+                .fetch_optional(&self.pool)
+                .await
+                .ok()?;
+        let (payload, stored_at, ttl) = row?;
+        if chrono::Utc::now().timestamp() - stored_at > ttl {
+            return None;
+        }
+        serde_json::from_str(&payload).ok()
+    }
+
+    async fn put(&self, key: &str, value: SiteMatch, ttl: u64) {
+        let now = chrono::Utc::now().timestamp();
+        if let Ok(payload) = serde_json::to_string(&value) {
+            let _ = sqlx::query(
+                "INSERT INTO price_cache (key, payload, stored_at, ttl)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(key) DO UPDATE SET payload = ?2, stored_at = ?3, ttl = ?4",
+            )
+            .bind(key)
+            .bind(&payload)
+            .bind(now)
+            .bind(ttl as i64)
+            .execute(&self.pool)
+            .await;
+        }
+
+        // Append-only price history for successful scrapes. `key` is always
+        // `cache_key(site, query)` (`"{site}:{normalized_query}"`), so strip the
+        // site prefix to recover the same normalized query `price_history` hashes.
+        if matches!(value.status.as_str(), "ok" | "ok_rendered") {
+            if let Some(price) = value.price {
+                let normalized_query = key
+                    .strip_prefix(&format!("{}:", value.site))
+                    .unwrap_or(key);
+                let hash = query_hash(normalized_query);
+                let _ = sqlx::query(
+                    "INSERT INTO price_history (site, query_hash, fetched_at, price)
+                     VALUES (?1, ?2, ?3, ?4)",
+                )
+                .bind(&value.site)
+                .bind(&hash)
+                .bind(now)
+                .bind(price)
+                .execute(&self.pool)
+                .await;
+            }
+        }
+    }
+}
+
+/// Observed price history, available only when the SQLite cache backend is active.
+#[cfg(feature = "sqlite")]
+pub async fn price_history(site: &str, query: &str, since_secs: i64) -> Option<PriceHistory> {
+    if std::env::var("CACHE_BACKEND").ok().as_deref() != Some("sqlite") {
+        return None;
+    }
+    let path = std::env::var("SQLITE_CACHE_PATH").unwrap_or_else(|_| "quote_cache.db".to_string());
+    let cache = SqliteQuoteCache::connect(&path).await.ok()?;
+    let since = chrono::Utc::now().timestamp() - since_secs;
+    Some(cache.price_history(site, query, since).await)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub async fn price_history(_site: &str, _query: &str, _since_secs: i64) -> Option<PriceHistory> {
+    None
+}
+
+/// Shared process-wide `QuoteCache`, chosen once from `CACHE_BACKEND`
+/// (`redis`/`s3`/`sqlite`), defaulting to the in-memory store.
+pub fn quote_cache() -> Arc<dyn QuoteCache> {
+    static CACHE: std::sync::OnceLock<Arc<dyn QuoteCache>> = std::sync::OnceLock::new();
+    CACHE
+        .get_or_init(|| {
+            let backend = std::env::var("CACHE_BACKEND").unwrap_or_default();
+            match backend.as_str() {
+                #[cfg(feature = "redis")]
+                "redis" => {
+                    if let Ok(url) = std::env::var("REDIS_URL") {
+                        if let Ok(cache) = RedisQuoteCache::connect(&url) {
+                            return Arc::new(cache) as Arc<dyn QuoteCache>;
+                        }
+                    }
+                    Arc::new(MemoryQuoteCache::default()) as Arc<dyn QuoteCache>
+                }
+                #[cfg(feature = "s3")]
+                "s3" => match S3QuoteCache::from_env() {
+                    Some(cache) => Arc::new(cache) as Arc<dyn QuoteCache>,
+                    None => Arc::new(MemoryQuoteCache::default()) as Arc<dyn QuoteCache>,
+                },
+                #[cfg(feature = "sqlite")]
+                "sqlite" => {
+                    let path = std::env::var("SQLITE_CACHE_PATH")
+                        .unwrap_or_else(|_| "quote_cache.db".to_string());
+                    match SqliteQuoteCache::connect_lazy(&path) {
+                        Ok(cache) => Arc::new(cache) as Arc<dyn QuoteCache>,
+                        Err(_) => Arc::new(MemoryQuoteCache::default()) as Arc<dyn QuoteCache>,
+                    }
+                }
+                _ => Arc::new(MemoryQuoteCache::default()) as Arc<dyn QuoteCache>,
+            }
+        })
+        .clone()
+}
+
+pub async fn scrape_site(
+    site: &str,
+    query: &str,
+    ttl: u64,
+    overrides: Option<&HashMap<String, String>>,
+    http: &HttpConfig,
+) -> SiteMatch {
     let start = Instant::now();
+    let key = cache_key(site, query);
+
+    if ttl > 0 {
+        if let Some(cached) = quote_cache().get(&key).await {
+            return SiteMatch {
+                status: "cached".to_string(),
+                latency_ms: Some(start.elapsed().as_millis()),
+                ..cached
+            };
+        }
+    }
 
     if let Some(cached) = maybe_get_cache(site, query, ttl).await {
+        if ttl > 0 {
+            quote_cache().put(&key, cached.clone(), ttl).await;
+        }
         return SiteMatch {
             status: "cached".to_string(),
             latency_ms: Some(start.elapsed().as_millis()),
@@ -435,45 +1338,57 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
         };
     }
 
+    // Prefer a structured JSON product API over regex-scraping HTML when the
+    // site exposes one; only fall back to HTML if the API path fails.
+    if let Some(result) = scrape_structured(site, query, overrides, http, start).await {
+        if ttl > 0 {
+            quote_cache().put(&key, result.clone(), ttl).await;
+        }
+        upsert_cache(site, query, &result).await;
+        return result;
+    }
+
     let url = site_url(site, query, overrides);
+    let max_attempts = http.max_retries + 1;
     let mut last_message = None::<String>;
     let mut final_body = None::<String>;
     let mut final_status = None::<u16>;
+    let mut retries = 0usize;
+    let mut rendered_path = false;
+    let mut last_proxy = None::<String>;
 
-    for attempt in 0..2usize {
+    for attempt in 0..max_attempts {
+        if attempt > 0 {
+            retries += 1;
+        }
         let ua = pick_user_agent(site, query, attempt);
-        let client = match reqwest::Client::builder().default_headers(build_headers_with_ua(ua)).build() {
-            Ok(c) => c,
-            Err(error) => {
-                return SiteMatch {
-                    site: site.to_string(),
-                    title: None,
-                    price: None,
-                    currency: Some("USD".to_string()),
-                    url: Some(url),
-                    status: "error".to_string(),
-                    message: Some(format!("client_init_failed: {error}")),
-                    latency_ms: Some(start.elapsed().as_millis())
-                }
-            }
-        };
+        let proxy = proxy_pool().pick(site, query, attempt);
+        last_proxy = proxy.clone();
+        let client = http_client_for(http.pool_max_idle_per_host, proxy.as_deref());
+        let is_last = attempt + 1 == max_attempts;
 
-        let timeout_secs = if attempt == 0 { 5 } else { 3 };
-        let request_result = timeout(Duration::from_secs(timeout_secs), client.get(&url).send()).await;
+        let request = client.get(&url).headers(build_headers_with_ua(ua));
+        let request_result = timeout(Duration::from_millis(http.timeout_ms), request.send()).await;
         let response = match request_result {
             Ok(Ok(res)) => res,
             Ok(Err(error)) => {
                 last_message = Some(error.to_string());
-                if attempt == 0 {
-                    tokio::time::sleep(Duration::from_millis(250)).await;
+                if let Some(proxy) = &proxy {
+                    proxy_pool().record_failure(proxy);
+                }
+                if !is_last {
+                    tokio::time::sleep(backoff_delay(attempt, site, query)).await;
                     continue;
                 }
                 break;
             }
             Err(_) => {
                 last_message = Some("timeout".to_string());
-                if attempt == 0 {
-                    tokio::time::sleep(Duration::from_millis(250)).await;
+                if let Some(proxy) = &proxy {
+                    proxy_pool().record_failure(proxy);
+                }
+                if !is_last {
+                    tokio::time::sleep(backoff_delay(attempt, site, query)).await;
                     continue;
                 }
                 break;
@@ -482,21 +1397,29 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
 
         let status = response.status().as_u16();
         final_status = Some(status);
-        if status == 403 || status == 429 || status == 503 {
+        if matches!(status, 403 | 429 | 502 | 503 | 504) {
             last_message = Some(format!("http_status_{status}"));
-            if attempt == 0 {
-                tokio::time::sleep(Duration::from_millis(300)).await;
+            if let Some(proxy) = &proxy {
+                proxy_pool().record_failure(proxy);
+            }
+            if !is_last {
+                let wait = retry_after(&response).unwrap_or_else(|| backoff_delay(attempt, site, query));
+                tokio::time::sleep(wait).await;
                 continue;
             }
             break;
         }
 
+        if let Some(proxy) = &proxy {
+            proxy_pool().record_success(proxy);
+        }
+
         let body = match read_body_limited(response, 512 * 1024).await {
             Ok(text) => text,
             Err(error) => {
                 last_message = Some(error);
-                if attempt == 0 {
-                    tokio::time::sleep(Duration::from_millis(200)).await;
+                if !is_last {
+                    tokio::time::sleep(backoff_delay(attempt, site, query)).await;
                     continue;
                 }
                 break;
@@ -505,10 +1428,17 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
 
         if likely_bot_challenge(&body.to_lowercase()) {
             last_message = Some("challenge_detected".to_string());
-            if attempt == 0 {
-                tokio::time::sleep(Duration::from_millis(300)).await;
+            if !is_last {
+                tokio::time::sleep(backoff_delay(attempt, site, query)).await;
                 continue;
             }
+            // Last resort: re-fetch through a headless browser so a JS
+            // challenge or client-rendered price can still be parsed.
+            if let Some(rendered) = headless_fetch(&url).await {
+                final_body = Some(rendered);
+                rendered_path = true;
+                break;
+            }
             return SiteMatch {
                 site: site.to_string(),
                 title: None,
@@ -516,18 +1446,38 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
                 currency: Some("USD".to_string()),
                 url: Some(url),
                 status: "unsupported_js".to_string(),
-                message: Some("site requires browser execution or anti-bot challenge".to_string()),
-                latency_ms: Some(start.elapsed().as_millis())
+                message: Some(format!(
+                    "site requires browser execution or anti-bot challenge (after {retries} retries)"
+                )),
+                latency_ms: Some(start.elapsed().as_millis()),
+                product_id: None
             };
         }
 
+        // A 200 with a plausible body but nothing parseable is often a site
+        // (especially VTEX) intermittently returning a near-empty page. Retry
+        // the remaining attempts with a rotated user agent before concluding
+        // `not_found`, as preciazo does — but never on a 404, where the query
+        // legitimately has no results.
+        if status != 404
+            && extract_price_from_body(site, &body).is_none()
+            && extract_title(&body).is_none()
+            && !is_last
+        {
+            last_message = Some("empty_body".to_string());
+            tokio::time::sleep(backoff_delay(attempt, site, query)).await;
+            continue;
+        }
+
         final_body = Some(body);
         break;
     }
 
     let body = if let Some(body) = final_body {
         body
-    } else if matches!(final_status, Some(403 | 429 | 503)) || matches!(last_message.as_deref(), Some("challenge_detected")) {
+    } else if matches!(final_status, Some(403 | 429 | 502 | 503 | 504))
+        || matches!(last_message.as_deref(), Some("challenge_detected"))
+    {
         return SiteMatch {
             site: site.to_string(),
             title: None,
@@ -535,8 +1485,15 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
             currency: Some("USD".to_string()),
             url: Some(url),
             status: "blocked".to_string(),
-            message: last_message.or_else(|| final_status.map(|s| format!("http_status_{s}"))),
-            latency_ms: Some(start.elapsed().as_millis())
+            message: Some(format!(
+                "{} (after {retries} retries{})",
+                last_message
+                    .or_else(|| final_status.map(|s| format!("http_status_{s}")))
+                    .unwrap_or_else(|| "blocked".to_string()),
+                proxy_suffix(&last_proxy)
+            )),
+            latency_ms: Some(start.elapsed().as_millis()),
+            product_id: None
         };
     } else {
         return SiteMatch {
@@ -546,16 +1503,42 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
             currency: Some("USD".to_string()),
             url: Some(url),
             status: "error".to_string(),
-            message: last_message.or(Some("request_failed".to_string())),
-            latency_ms: Some(start.elapsed().as_millis())
+            message: Some(format!(
+                "{} (after {retries} retries{})",
+                last_message.unwrap_or_else(|| "request_failed".to_string()),
+                proxy_suffix(&last_proxy)
+            )),
+            latency_ms: Some(start.elapsed().as_millis()),
+            product_id: None
         };
     };
 
-    let title = extract_title(&body);
-    let price = extract_price_from_body(site, &body);
-    let result_url = extract_result_url(site, &body);
+    let mut title = extract_title(&body);
+    let mut price = extract_price_from_body(site, &body);
+    let mut result_url = extract_result_url(site, &body);
+    let mut product_id = extract_product_id(site, &body);
+
+    // A 200 with a valid body but no extractable price is often a
+    // client-rendered page; fall back to the headless driver before giving up,
+    // or whenever the adapter declares the site needs JS.
+    if (price.is_none() || site_requires_js(site)) && !rendered_path && headless_enabled() {
+        if let Some(rendered) = headless_fetch(&url).await {
+            if let Some(rendered_price) = extract_price_from_body(site, &rendered) {
+                price = Some(rendered_price);
+                title = extract_title(&rendered).or(title);
+                result_url = extract_result_url(site, &rendered).or(result_url);
+                product_id = extract_product_id(site, &rendered).or(product_id);
+                rendered_path = true;
+            }
+        }
+    }
 
-    let status = if price.is_some() { "ok" } else { "not_found" }.to_string();
+    let status = match (price.is_some(), rendered_path) {
+        (true, true) => "ok_rendered",
+        (true, false) => "ok",
+        (false, _) => "not_found",
+    }
+    .to_string();
 
     let result = SiteMatch {
         site: site.to_string(),
@@ -564,23 +1547,252 @@ pub async fn scrape_site(site: &str, query: &str, ttl: u64, overrides: Option<&H
         currency: Some("USD".to_string()),
         url: result_url.or(Some(url)),
         status,
-        message: None,
-        latency_ms: Some(start.elapsed().as_millis())
+        message: if retries > 0 {
+            Some(format!("succeeded after {retries} retries"))
+        } else {
+            None
+        },
+        latency_ms: Some(start.elapsed().as_millis()),
+        product_id
     };
 
-    if matches!(result.status.as_str(), "ok" | "not_found") {
+    if matches!(result.status.as_str(), "ok" | "ok_rendered" | "not_found") {
+        if ttl > 0 {
+            quote_cache().put(&key, result.clone(), ttl).await;
+        }
         upsert_cache(site, query, &result).await;
     }
 
     result
 }
 
+/// Durable log of the SSE events emitted by a single run, keyed by `run_id`.
+#[async_trait::async_trait]
+pub trait JobStore: Send + Sync {
+    async fn append(&self, run_id: &str, event: serde_json::Value) -> u64;
+    async fn events_after(&self, run_id: &str, after: u64) -> Vec<(u64, serde_json::Value)>;
+    async fn finish(&self, run_id: &str);
+    async fn is_finished(&self, run_id: &str) -> bool;
+
+    /// Resolve once new events may be available; callers must re-check after awaiting.
+    async fn notified(&self, run_id: &str);
+}
+
+struct RunLog {
+    events: Vec<(u64, serde_json::Value)>,
+    finished: bool,
+}
+
+struct RunState {
+    log: std::sync::Mutex<RunLog>,
+    notify: tokio::sync::Notify,
+}
+
+/// Process-local `JobStore`, scoped to a single warm instance.
+#[derive(Default)]
+pub struct InMemoryJobStore {
+    runs: std::sync::Mutex<HashMap<String, Arc<RunState>>>,
+}
+
+impl InMemoryJobStore {
+    fn state(&self, run_id: &str) -> Arc<RunState> {
+        let mut runs = self.runs.lock().unwrap();
+        runs.entry(run_id.to_string())
+            .or_insert_with(|| {
+                Arc::new(RunState {
+                    log: std::sync::Mutex::new(RunLog {
+                        events: Vec::new(),
+                        finished: false,
+                    }),
+                    notify: tokio::sync::Notify::new(),
+                })
+            })
+            .clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl JobStore for InMemoryJobStore {
+    async fn append(&self, run_id: &str, event: serde_json::Value) -> u64 {
+        let state = self.state(run_id);
+        let seq = {
+            let mut log = state.log.lock().unwrap();
+            let seq = log.events.len() as u64 + 1;
+            log.events.push((seq, event));
+            seq
+        };
+        state.notify.notify_waiters();
+        seq
+    }
+
+    async fn events_after(&self, run_id: &str, after: u64) -> Vec<(u64, serde_json::Value)> {
+        let state = self.state(run_id);
+        let log = state.log.lock().unwrap();
+        log.events
+            .iter()
+            .filter(|(seq, _)| *seq > after)
+            .cloned()
+            .collect()
+    }
+
+    async fn finish(&self, run_id: &str) {
+        let state = self.state(run_id);
+        state.log.lock().unwrap().finished = true;
+        state.notify.notify_waiters();
+    }
+
+    async fn is_finished(&self, run_id: &str) -> bool {
+        let state = self.state(run_id);
+        let finished = state.log.lock().unwrap().finished;
+        finished
+    }
+
+    async fn notified(&self, run_id: &str) {
+        let state = self.state(run_id);
+        state.notify.notified().await;
+    }
+}
+
+/// Redis-backed `JobStore`, selected when `REDIS_URL` is set.
+#[cfg(feature = "redis")]
+pub struct RedisJobStore {
+    client: redis::Client,
+}
+
+#[cfg(feature = "redis")]
+impl RedisJobStore {
+    pub fn connect(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+        })
+    }
+
+    fn events_key(run_id: &str) -> String {
+        format!("quote:run:{run_id}:events")
+    }
+
+    fn done_key(run_id: &str) -> String {
+        format!("quote:run:{run_id}:done")
+    }
+
+    fn channel(run_id: &str) -> String {
+        format!("quote:run:{run_id}:wake")
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait::async_trait]
+impl JobStore for RedisJobStore {
+    async fn append(&self, run_id: &str, event: serde_json::Value) -> u64 {
+        let mut conn = match self.client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(_) => return 0,
+        };
+        let seq: u64 = redis::cmd("INCR")
+            .arg(format!("quote:run:{run_id}:seq"))
+            .query_async(&mut conn)
+            .await
+            .unwrap_or(0);
+        let entry = json!({ "seq": seq, "event": event }).to_string();
+        let _: Result<(), _> = redis::cmd("RPUSH")
+            .arg(Self::events_key(run_id))
+            .arg(&entry)
+            .query_async(&mut conn)
+            .await;
+        let _: Result<(), _> = redis::cmd("PUBLISH")
+            .arg(Self::channel(run_id))
+            .arg(seq)
+            .query_async(&mut conn)
+            .await;
+        seq
+    }
+
+    async fn events_after(&self, run_id: &str, after: u64) -> Vec<(u64, serde_json::Value)> {
+        let mut conn = match self.client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(_) => return Vec::new(),
+        };
+        let raw: Vec<String> = redis::cmd("LRANGE")
+            .arg(Self::events_key(run_id))
+            .arg(0)
+            .arg(-1)
+            .query_async(&mut conn)
+            .await
+            .unwrap_or_default();
+        raw.into_iter()
+            .filter_map(|entry| serde_json::from_str::<serde_json::Value>(&entry).ok())
+            .filter_map(|value| {
+                let seq = value.get("seq")?.as_u64()?;
+                let event = value.get("event")?.clone();
+                Some((seq, event))
+            })
+            .filter(|(seq, _)| *seq > after)
+            .collect()
+    }
+
+    async fn finish(&self, run_id: &str) {
+        if let Ok(mut conn) = self.client.get_multiplexed_async_connection().await {
+            let _: Result<(), _> = redis::cmd("SET")
+                .arg(Self::done_key(run_id))
+                .arg(1)
+                .query_async(&mut conn)
+                .await;
+            let _: Result<(), _> = redis::cmd("PUBLISH")
+                .arg(Self::channel(run_id))
+                .arg("done")
+                .query_async(&mut conn)
+                .await;
+        }
+    }
+
+    async fn is_finished(&self, run_id: &str) -> bool {
+        let Ok(mut conn) = self.client.get_multiplexed_async_connection().await else {
+            return false;
+        };
+        redis::cmd("EXISTS")
+            .arg(Self::done_key(run_id))
+            .query_async::<bool>(&mut conn)
+            .await
+            .unwrap_or(false)
+    }
+
+    async fn notified(&self, _run_id: &str) {
+        // The tailer re-polls on a short timeout, so a coarse wakeup is
+        // sufficient; a full pub/sub subscription would pin a connection
+        // per tail and is not worth it for this workload.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Shared process-wide `JobStore`, chosen once from `REDIS_URL`.
+pub fn job_store() -> Arc<dyn JobStore> {
+    static STORE: std::sync::OnceLock<Arc<dyn JobStore>> = std::sync::OnceLock::new();
+    STORE
+        .get_or_init(|| {
+            #[cfg(feature = "redis")]
+            if let Ok(url) = std::env::var("REDIS_URL") {
+                if let Ok(store) = RedisJobStore::connect(&url) {
+                    return Arc::new(store) as Arc<dyn JobStore>;
+                }
+            }
+            Arc::new(InMemoryJobStore::default()) as Arc<dyn JobStore>
+        })
+        .clone()
+}
+
+/// Absolute distance between a cluster's median price and the overall median.
+fn cluster_median_gap(cluster: &[(String, f64, String)], overall_median: f64) -> f64 {
+    let mut prices: Vec<f64> = cluster.iter().map(|(_, p, _)| *p).collect();
+    prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    (prices[prices.len() / 2] - overall_median).abs()
+}
+
 pub fn best_from_matches(matches: &[SiteMatch]) -> Option<BestMatch> {
-    let valid: Vec<(String, f64, String)> = matches
+    let valid: Vec<(String, f64, String, Option<String>)> = matches
         .iter()
         .filter_map(|entry| {
-            if entry.status == "ok" {
-                Some((entry.site.clone(), entry.price?, entry.url.clone()?))
+            if matches!(entry.status.as_str(), "ok" | "ok_rendered") {
+                Some((entry.site.clone(), entry.price?, entry.url.clone()?, entry.product_id.clone()))
             } else {
                 None
             }
@@ -591,6 +1803,56 @@ pub fn best_from_matches(matches: &[SiteMatch]) -> Option<BestMatch> {
         return None;
     }
 
+    // When sites agree on a product identifier, compare prices only within the
+    // dominant cluster (the id shared by the most sites, tie-broken by price
+    // proximity to the overall median) so an accessory or a different capacity
+    // can't masquerade as the cheapest match.
+    let overall_median = {
+        let mut all: Vec<f64> = valid.iter().map(|(_, p, _, _)| *p).collect();
+        all.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        all[all.len() / 2]
+    };
+    let mut clusters: HashMap<String, Vec<(String, f64, String)>> = HashMap::new();
+    let mut no_id_count = 0usize;
+    for (site, price, url, product_id) in &valid {
+        match product_id {
+            Some(id) => {
+                clusters
+                    .entry(id.clone())
+                    .or_default()
+                    .push((site.clone(), *price, url.clone()));
+            }
+            None => no_id_count += 1,
+        }
+    }
+    // A single incidental id (common, since id extraction is best-effort
+    // JSON-LD/ASIN/model scraping) must not outrank the rest of the field: the
+    // cluster needs at least two sites agreeing, and has to out-number the
+    // sites that couldn't produce an id at all before it's trusted over the
+    // median-floor fallback below.
+    if let Some((_, dominant)) = clusters
+        .into_iter()
+        .filter(|(_, cluster)| cluster.len() >= 2 && cluster.len() > no_id_count)
+        .max_by(|(_, a), (_, b)| {
+            a.len().cmp(&b.len()).then_with(|| {
+                let a_gap = cluster_median_gap(a, overall_median);
+                let b_gap = cluster_median_gap(b, overall_median);
+                b_gap.partial_cmp(&a_gap).unwrap_or(std::cmp::Ordering::Equal)
+            })
+        })
+    {
+        return dominant
+            .into_iter()
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(site, price, url)| BestMatch { site, price, url });
+    }
+
+    // No qualifying product-id cluster: fall back to the median-floor heuristic.
+    let valid: Vec<(String, f64, String)> = valid
+        .into_iter()
+        .map(|(site, price, url, _)| (site, price, url))
+        .collect();
+
     let mut prices: Vec<f64> = valid.iter().map(|(_, p, _)| *p).collect();
     prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
 
@@ -613,18 +1875,151 @@ pub fn best_from_matches(matches: &[SiteMatch]) -> Option<BestMatch> {
         .map(|(site, price, url)| BestMatch { site, price, url })
 }
 
+/// The completion webhook URL for a run, if the caller opted in.
+pub fn callback_url(req: &QuoteRequest) -> Option<String> {
+    req.options.as_ref().and_then(|options| options.callback_url.clone())
+}
+
+/// `sha256=`-prefixed hex HMAC-SHA256 of `body` under `WEBHOOK_SECRET` (or
+/// `QUOTE_WEBHOOK_SECRET`), `None` if neither is set.
+fn sign_payload(body: &[u8]) -> Option<String> {
+    use hmac::{Hmac, Mac};
+    let secret = std::env::var("WEBHOOK_SECRET")
+        .ok()
+        .or_else(|| std::env::var("QUOTE_WEBHOOK_SECRET").ok())?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).ok()?;
+    mac.update(body);
+    Some(format!("sha256={:x}", mac.finalize().into_bytes()))
+}
+
+/// True if `ip` falls in loopback, private, link-local, or other non-public address space.
+fn is_blocked_webhook_ip(ip: &std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+        }
+        std::net::IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.to_ipv4_mapped().is_some_and(|v4| is_blocked_webhook_ip(&std::net::IpAddr::V4(v4)))
+                || (v6.segments()[0] & 0xfe00) == 0xfc00 // unique local, fc00::/7
+                || (v6.segments()[0] & 0xffc0) == 0xfe80 // link-local, fe80::/10
+        }
+    }
+}
+
+/// Validate `url` as a safe webhook target and pin it to the `SocketAddr` to
+/// connect to, so a later DNS-rebind can't bypass this check.
+async fn resolve_allowed_webhook_url(url: &reqwest::Url) -> Option<(String, std::net::SocketAddr)> {
+    if url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host_str()?;
+    if host.eq_ignore_ascii_case("localhost") || host.eq_ignore_ascii_case("metadata.google.internal") {
+        return None;
+    }
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<_> = tokio::net::lookup_host((host, port)).await.ok()?.collect();
+    let addr = *addrs.first()?;
+    if addrs.iter().any(|addr| is_blocked_webhook_ip(&addr.ip())) {
+        return None;
+    }
+    Some((host.to_string(), addr))
+}
+
+/// Deliver a finished run to its `callback_url` with bounded, signed retries.
+/// Meant to be `tokio::spawn`ed so it never blocks the response.
+pub async fn deliver_webhook(url: String, run_id: String, body: Vec<u8>) {
+    let Ok(parsed) = reqwest::Url::parse(&url) else {
+        return;
+    };
+    let Some((host, addr)) = resolve_allowed_webhook_url(&parsed).await else {
+        return;
+    };
+    let Ok(client) = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+    else {
+        return;
+    };
+
+    let max_attempts = std::env::var("WEBHOOK_MAX_RETRIES")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .map(|retries| retries.saturating_add(1))
+        .unwrap_or(5);
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let signature = sign_payload(&body);
+
+    for attempt in 0..max_attempts {
+        let mut request = client
+            .post(&url)
+            .header("content-type", "application/json")
+            .header("X-Run-Id", &run_id)
+            .header("X-Timestamp", &timestamp);
+        if let Some(signature) = &signature {
+            request = request.header("X-Signature", signature);
+        }
+
+        match request.body(body.clone()).send().await {
+            Ok(response) if response.status().is_success() => return,
+            _ => {
+                if attempt + 1 == max_attempts {
+                    return;
+                }
+                let wait = (200u64.saturating_mul(1 << attempt.min(4))).min(5000);
+                tokio::time::sleep(Duration::from_millis(wait)).await;
+            }
+        }
+    }
+}
+
+/// Per-run dedup of repeated queries within a single [`run_quote_collect`] call.
+#[derive(Default)]
+struct RunQueryDedup {
+    seen: HashMap<String, Vec<SiteMatch>>,
+}
+
+impl RunQueryDedup {
+    fn get(&self, query: &str) -> Option<&Vec<SiteMatch>> {
+        self.seen.get(query)
+    }
+
+    fn insert(&mut self, query: String, matches: Vec<SiteMatch>) {
+        self.seen.insert(query, matches);
+    }
+}
+
 pub async fn run_quote_collect(request: &QuoteRequest) -> Result<Vec<ItemResult>> {
     let ttl = ttl_seconds(request);
+    let http = http_config(request);
     let semaphore = Arc::new(Semaphore::new(20));
     let overrides = request.site_overrides.clone();
 
     let mut item_results = Vec::with_capacity(request.items.len());
+    let mut by_query = RunQueryDedup::default();
 
     for item in &request.items {
+        if let Some(matches) = by_query.get(&item.query) {
+            let best = best_from_matches(matches);
+            item_results.push(ItemResult {
+                query: item.query.clone(),
+                matches: matches.clone(),
+                best,
+            });
+            continue;
+        }
         let tasks = request.site_plan.iter().cloned().map(|site| {
             let sem = semaphore.clone();
             let query = item.query.clone();
             let overrides = overrides.clone();
+            let http = http;
             async move {
                 let permit = sem.acquire_owned().await;
                 let Ok(_permit) = permit else {
@@ -636,24 +2031,34 @@ pub async fn run_quote_collect(request: &QuoteRequest) -> Result<Vec<ItemResult>
                         url: Some(site_url(&site, &query, overrides.as_ref())),
                         status: "error".to_string(),
                         message: Some("semaphore_closed".to_string()),
-                        latency_ms: Some(0)
+                        latency_ms: Some(0),
+                        product_id: None
                     };
                     return (site, fallback);
                 };
-                let result = scrape_site(&site, &query, ttl, overrides.as_ref()).await;
+                let result = scrape_site(&site, &query, ttl, overrides.as_ref(), &http).await;
                 (site, result)
             }
         });
 
+        let sem = semaphore.clone();
         let mut matches: Vec<SiteMatch> = stream::iter(tasks)
             .buffer_unordered(20)
-            .then(|(_, result)| async move { result })
+            .then(move |(_, result)| {
+                let sem = sem.clone();
+                async move {
+                    record_scrape(&result.site, &result.status, result.latency_ms);
+                    record_inflight(20 - sem.available_permits());
+                    result
+                }
+            })
             .collect()
             .await;
 
         matches.sort_by(|a, b| a.site.cmp(&b.site));
         let best = best_from_matches(&matches);
 
+        by_query.insert(item.query.clone(), matches.clone());
         item_results.push(ItemResult {
             query: item.query.clone(),
             matches,
@@ -663,3 +2068,209 @@ pub async fn run_quote_collect(request: &QuoteRequest) -> Result<Vec<ItemResult>
 
     Ok(item_results)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_with_options(options: QuoteOptions) -> QuoteRequest {
+        QuoteRequest {
+            run_id: None,
+            items: vec![],
+            category: "test".to_string(),
+            site_plan: vec![],
+            site_overrides: None,
+            options: Some(options),
+        }
+    }
+
+    fn no_options(max_retries: Option<usize>, timeout_ms: Option<u64>, pool_max_idle_per_host: Option<usize>) -> QuoteOptions {
+        QuoteOptions {
+            cache_ttl: None,
+            heartbeat_secs: None,
+            max_retries,
+            timeout_ms,
+            pool_max_idle_per_host,
+            callback_url: None,
+        }
+    }
+
+    #[test]
+    fn http_config_defaults_without_options() {
+        let req = QuoteRequest {
+            run_id: None,
+            items: vec![],
+            category: "test".to_string(),
+            site_plan: vec![],
+            site_overrides: None,
+            options: None,
+        };
+        let config = http_config(&req);
+        assert_eq!(config.max_retries, HttpConfig::default().max_retries);
+        assert_eq!(config.timeout_ms, HttpConfig::default().timeout_ms);
+        assert_eq!(config.pool_max_idle_per_host, HttpConfig::default().pool_max_idle_per_host);
+    }
+
+    #[test]
+    fn http_config_clamps_max_retries_to_ceiling() {
+        let req = request_with_options(no_options(Some(usize::MAX), None, None));
+        assert_eq!(http_config(&req).max_retries, MAX_RETRIES_CEILING);
+    }
+
+    #[test]
+    fn http_config_clamps_timeout_ms_to_ceiling_and_floor() {
+        let high = request_with_options(no_options(None, Some(u64::MAX), None));
+        assert_eq!(http_config(&high).timeout_ms, TIMEOUT_MS_CEILING);
+
+        let zero = request_with_options(no_options(None, Some(0), None));
+        assert_eq!(http_config(&zero).timeout_ms, 1);
+    }
+
+    #[test]
+    fn http_config_clamps_pool_max_idle_per_host() {
+        let high = request_with_options(no_options(None, None, Some(usize::MAX)));
+        assert_eq!(http_config(&high).pool_max_idle_per_host, POOL_MAX_IDLE_PER_HOST_CEILING);
+
+        let zero = request_with_options(no_options(None, None, Some(0)));
+        assert_eq!(http_config(&zero).pool_max_idle_per_host, 1);
+    }
+
+    #[test]
+    fn http_config_honors_in_bounds_caller_values() {
+        let req = request_with_options(no_options(Some(1), Some(2000), Some(4)));
+        let config = http_config(&req);
+        assert_eq!(config.max_retries, 1);
+        assert_eq!(config.timeout_ms, 2000);
+        assert_eq!(config.pool_max_idle_per_host, 4);
+    }
+
+    // `scrape_site`'s parse-failure retry (src/shared.rs) fires when a 200
+    // response yields neither a title nor a price; these are the two pure
+    // extractors that decide that.
+    #[test]
+    fn empty_body_has_no_title_or_price() {
+        assert_eq!(extract_title(""), None);
+        assert_eq!(extract_price_from_body("amazon", ""), None);
+    }
+
+    #[test]
+    fn body_with_title_and_price_is_parseable() {
+        let body = r#"<html><head><title>Widget</title></head><body>$19.99</body></html>"#;
+        assert_eq!(extract_title(body), Some("Widget".to_string()));
+        assert_eq!(extract_price_from_body("unknown_site", body), Some(19.99));
+    }
+
+    fn ok_match(site: &str, price: f64, product_id: Option<&str>) -> SiteMatch {
+        SiteMatch {
+            site: site.to_string(),
+            title: None,
+            price: Some(price),
+            currency: Some("USD".to_string()),
+            url: Some(format!("https://{site}.example/p")),
+            status: "ok".to_string(),
+            message: None,
+            latency_ms: Some(10),
+            product_id: product_id.map(|id| id.to_string()),
+        }
+    }
+
+    #[test]
+    fn best_from_matches_falls_back_to_median_floor_without_ids() {
+        let matches = vec![
+            ok_match("a", 10.0, None),
+            ok_match("b", 100.0, None),
+            ok_match("c", 105.0, None),
+        ];
+        // median of [10, 100, 105] is 100, floor is 40: the 10.0 outlier is
+        // dropped, leaving 100 as the cheapest surviving match.
+        let best = best_from_matches(&matches).unwrap();
+        assert_eq!(best.site, "b");
+        assert_eq!(best.price, 100.0);
+    }
+
+    #[test]
+    fn best_from_matches_prefers_dominant_cluster_over_cheaper_no_id_match() {
+        let matches = vec![
+            ok_match("a", 10.0, None),
+            ok_match("b", 50.0, Some("sku-1")),
+            ok_match("c", 60.0, Some("sku-1")),
+        ];
+        // Two sites agree on sku-1, out-numbering the single no-id match, so
+        // the cluster wins even though "a" is cheaper.
+        let best = best_from_matches(&matches).unwrap();
+        assert_eq!(best.site, "b");
+        assert_eq!(best.price, 50.0);
+    }
+
+    #[test]
+    fn best_from_matches_ignores_lone_incidental_id() {
+        let matches = vec![
+            ok_match("a", 200.0, Some("sku-1")),
+            ok_match("b", 20.0, None),
+            ok_match("c", 25.0, None),
+        ];
+        // A single id-bearing match must not outrank a larger no-id group:
+        // falls through to the median-floor heuristic over all valid matches.
+        let best = best_from_matches(&matches).unwrap();
+        assert_eq!(best.site, "b");
+        assert_eq!(best.price, 20.0);
+    }
+
+    #[test]
+    fn best_from_matches_breaks_cluster_ties_by_median_proximity() {
+        let matches = vec![
+            ok_match("a", 100.0, Some("sku-1")),
+            ok_match("b", 102.0, Some("sku-1")),
+            ok_match("c", 10.0, Some("sku-2")),
+            ok_match("d", 12.0, Some("sku-2")),
+        ];
+        // Both clusters have 2 sites; overall median of [10,12,100,102] is 100,
+        // so sku-1 (median 102) beats sku-2 (median 12) on proximity.
+        let best = best_from_matches(&matches).unwrap();
+        assert_eq!(best.site, "a");
+        assert_eq!(best.price, 100.0);
+    }
+
+    #[test]
+    fn best_from_matches_ignores_non_ok_statuses() {
+        let mut errored = ok_match("a", 1.0, None);
+        errored.status = "not_found".to_string();
+        assert!(best_from_matches(&[errored]).is_none());
+    }
+
+    #[test]
+    fn amazon_adapter_builds_search_url_and_extracts_product_link() {
+        let adapter = AmazonAdapter;
+        assert_eq!(adapter.search_url("wd red 4tb"), "https://www.amazon.com/s?k=wd%20red%204tb");
+        assert!(!adapter.requires_js());
+
+        let body = r#"<a href="/Example-Widget/dp/B000000000">Widget</a>"#;
+        assert_eq!(
+            adapter.extract_product_url(body),
+            Some("https://www.amazon.com/Example-Widget/dp/B000000000".to_string())
+        );
+        assert_eq!(adapter.extract_product_url("no links here"), None);
+    }
+
+    #[test]
+    fn generic_adapter_fills_in_the_query_template() {
+        let adapter = GenericAdapter {
+            template: "https://www.newegg.com/p/pl?d={q}",
+            product_url: newegg_product_url,
+        };
+        assert_eq!(adapter.search_url("rtx 4070"), "https://www.newegg.com/p/pl?d=rtx%204070");
+    }
+
+    #[test]
+    fn google_adapter_is_the_fallback_for_unknown_sites() {
+        assert_eq!(
+            adapter_for("some_site_not_in_the_registry").search_url("thing"),
+            GoogleAdapter.search_url("thing")
+        );
+    }
+
+    #[test]
+    fn adapter_for_known_site_uses_its_registered_template() {
+        assert_eq!(adapter_for("bestbuy").search_url("tv"), "https://www.bestbuy.com/site/searchpage.jsp?st=tv");
+    }
+}